@@ -5,6 +5,8 @@
 //! register (or set of related registers) defined in the
 //! [datasheet](http://www.st.com/resource/en/datasheet/hts221.pdf).
 
+use embedded_hal::blocking::delay::DelayUs;
+
 /// 7-bit I2C slave address of the HTS221.  Note that the datasheet includes the 8-bit read address
 /// (BFh) and 8-bit write address (BEh).
 const I2C_ID: u8 = 0x5F;
@@ -27,6 +29,31 @@ pub trait I2C {
     ) -> Result<(), Self::Error>;
 }
 
+/// Blanket implementation of [`I2C`] for any type that already implements the standard
+/// `embedded-hal` blocking I2C traits, so HAL types (`stm32f4xx-hal`, `nrf-hal`, `rppal`, ...) can
+/// be plugged in directly, without writing an adapter.  Enabled by the `embedded-hal` feature.
+#[cfg(feature = "embedded-hal")]
+impl<Comm, E> I2C for Comm
+where
+    Comm: embedded_hal::blocking::i2c::Write<Error = E>
+        + embedded_hal::blocking::i2c::WriteRead<Error = E>,
+{
+    type Error = E;
+
+    fn write(&mut self, dev_addr: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        embedded_hal::blocking::i2c::Write::write(self, dev_addr, bytes)
+    }
+
+    fn write_read(
+        &mut self,
+        dev_addr: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        embedded_hal::blocking::i2c::WriteRead::write_read(self, dev_addr, bytes, buffer)
+    }
+}
+
 fn read_register<Comm: I2C>(comm: &mut Comm, addr: u8) -> Result<u8, Comm::Error> {
     let mut data: [u8; 1] = [0];
     comm.write_read(I2C_ID, &[addr], &mut data)?;
@@ -43,6 +70,42 @@ fn read_register_pair<Comm: I2C>(comm: &mut Comm, addr: u8) -> Result<i16, Comm:
     Ok(((data[1] as i16) << 8) | (data[0] as i16))
 }
 
+/// Error returned by helpers that poll a STATUS bit with a retry budget: either the underlying
+/// I2C transaction failed, or the bit never became set before the budget expired.
+#[derive(Debug)]
+pub enum PollError<E> {
+    I2C(E),
+    Timeout,
+}
+
+impl<E> From<E> for PollError<E> {
+    fn from(e: E) -> Self {
+        PollError::I2C(e)
+    }
+}
+
+/// Calls `ready` up to `max_retries` times, sleeping `retry_delay_us` microseconds (via `delay`)
+/// between attempts, until it returns `true`.  Returns `Err(PollError::Timeout)` if `ready` never
+/// does.
+fn poll_until<E, Delay, F>(
+    delay: &mut Delay,
+    retry_delay_us: u32,
+    max_retries: u32,
+    mut ready: F,
+) -> Result<(), PollError<E>>
+where
+    Delay: DelayUs<u32>,
+    F: FnMut() -> Result<bool, E>,
+{
+    for _ in 0..max_retries {
+        if ready()? {
+            return Ok(());
+        }
+        delay.delay_us(retry_delay_us);
+    }
+    Err(PollError::Timeout)
+}
+
 /// The WHO_AM_I register, for device identification.
 pub struct WhoAmI(u8);
 
@@ -156,13 +219,13 @@ impl AvConf {
     /// sample.  Use inside a `modify` function to actually set the value on the chip.
     ///
     /// Do this:
-    /// ```
+    /// ```ignore
     /// let av_conf = hts221.av_conf()?;
     /// av_conf.modify(|w| w.set_humidity_samples_averaged(AvgH::Avg8))?;
     /// ```
     ///
     /// Instead of this:
-    /// ```
+    /// ```ignore
     /// let av_conf = hts221.av_conf()?;
     /// av_conf.set_humidity_samples_averaged(AvgH::Avg8)?;  // not written to chip
     /// ```
@@ -223,6 +286,24 @@ pub mod cr1 {
         Continuous7Hz = 0b10,
         Continuous12_5Hz = 0b11,
     }
+
+    impl DataRate {
+        /// Returns a `(retry_delay_us, max_retries)` budget for polling STATUS with
+        /// [`super::HumidityOut::read_when_ready`] or [`super::TemperatureOut::read_when_ready`],
+        /// sized so the total wait comfortably covers one conversion period at this data rate.
+        pub fn conversion_timeout(&self) -> (u32, u32) {
+            match self {
+                // A one-shot conversion completes in well under 50 ms.
+                DataRate::OneShot => (1_000, 50),
+                // 1 Hz: one sample roughly every second.
+                DataRate::Continuous1Hz => (10_000, 150),
+                // 7 Hz: one sample roughly every 143 ms.
+                DataRate::Continuous7Hz => (2_000, 100),
+                // 12.5 Hz: one sample roughly every 80 ms.
+                DataRate::Continuous12_5Hz => (1_000, 100),
+            }
+        }
+    }
 }
 impl CtrlReg1 {
     /// Blocking read of the CTRL_REG1 register over `comm`.
@@ -246,7 +327,7 @@ impl CtrlReg1 {
 
     /// Returns true if the chip is active.
     pub fn is_powered_up(&self) -> bool {
-        (self.0 & cr1::PD_BIT) > 0
+        (self.0 & (1 << cr1::PD_BIT)) > 0
     }
 
     /// Clears the power-down bit.  The device is in power-down mode when PD = 0.
@@ -261,7 +342,7 @@ impl CtrlReg1 {
 
     /// Returns true if the chip is using block-update mode.
     pub fn is_block_update(&self) -> bool {
-        (self.0 & cr1::BDU_BIT) > 0
+        (self.0 & (1 << cr1::BDU_BIT)) > 0
     }
 
     /// Clears the block-update mode bit.  In default (continuous) mode, the lower and upper parts
@@ -336,7 +417,7 @@ impl CtrlReg2 {
 
     /// Returns true if the chip is booting.
     pub fn is_booting(&self) -> bool {
-        (self.0 & cr2::BOOT_BIT) > 0
+        (self.0 & (1 << cr2::BOOT_BIT)) > 0
     }
 
     /// Sets the boot bit.  From the datasheet:
@@ -356,7 +437,7 @@ impl CtrlReg2 {
 
     /// Returns true if the heating element is on.
     pub fn is_heater_on(&self) -> bool {
-        (self.0 & cr2::HEATER_BIT) > 0
+        (self.0 & (1 << cr2::HEATER_BIT)) > 0
     }
 
     /// Enables the heating element.
@@ -371,7 +452,7 @@ impl CtrlReg2 {
 
     /// Returns true if a one-shot conversion is pending.
     pub fn is_one_shot(&self) -> bool {
-        (self.0 & cr2::ONE_SHOT_BIT) > 0
+        (self.0 & (1 << cr2::ONE_SHOT_BIT)) > 0
     }
 
     /// Initiates a one-shot conversion.  The bit will be cleared by hardware after the conversion
@@ -508,6 +589,27 @@ impl HumidityOut {
     pub fn value(&self) -> i16 {
         self.0
     }
+
+    /// Polls STATUS for up to `max_retries` attempts, sleeping `retry_delay_us` microseconds
+    /// (via `delay`) between each, until humidity data is available, then performs the blocking
+    /// read.  Spares the caller from busy-looping on [`StatusReg::humidity_data_available`], and
+    /// makes it safe to read right after a one-shot conversion is triggered, since the ONE_SHOT
+    /// bit clears asynchronously.  Returns [`PollError::Timeout`] if the budget expires first.
+    pub fn read_when_ready<Comm, Delay>(
+        comm: &mut Comm,
+        delay: &mut Delay,
+        retry_delay_us: u32,
+        max_retries: u32,
+    ) -> Result<Self, PollError<Comm::Error>>
+    where
+        Comm: I2C,
+        Delay: DelayUs<u32>,
+    {
+        poll_until(delay, retry_delay_us, max_retries, || {
+            Ok(StatusReg::new(comm)?.humidity_data_available())
+        })?;
+        Ok(Self::new(comm)?)
+    }
 }
 
 /// Combination of TEMP_OUT_L and TEMP_OUT_H registers.
@@ -534,6 +636,25 @@ impl TemperatureOut {
     pub fn value(&self) -> i16 {
         self.0
     }
+
+    /// Polls STATUS for up to `max_retries` attempts, sleeping `retry_delay_us` microseconds
+    /// (via `delay`) between each, until temperature data is available, then performs the
+    /// blocking read.  Returns [`PollError::Timeout`] if the budget expires first.
+    pub fn read_when_ready<Comm, Delay>(
+        comm: &mut Comm,
+        delay: &mut Delay,
+        retry_delay_us: u32,
+        max_retries: u32,
+    ) -> Result<Self, PollError<Comm::Error>>
+    where
+        Comm: I2C,
+        Delay: DelayUs<u32>,
+    {
+        poll_until(delay, retry_delay_us, max_retries, || {
+            Ok(StatusReg::new(comm)?.temperature_data_available())
+        })?;
+        Ok(Self::new(comm)?)
+    }
 }
 
 /// Calibration data for the particular chip.  All chips are factory-calibrated, and require no
@@ -580,4 +701,194 @@ impl Calibration {
             t1_out: (data[15] as i16) << 8 | data[14] as i16,
         })
     }
+
+    /// Converts a raw `HumidityOut` value into relative humidity, in percent, using this chip's
+    /// factory calibration.  Returns `None` if the two calibration points share the same ADC
+    /// reading, which would require dividing by zero and indicates an uninitialized or faulty
+    /// part.
+    pub fn relative_humidity(&self, h_out: i16) -> Option<f32> {
+        let denom = self.h1_t0_out as i32 - self.h0_t0_out as i32;
+        if denom == 0 {
+            return None;
+        }
+        let h0_rh = self.h0_rh_x2 as f32 / 2.0;
+        let h1_rh = self.h1_rh_x2 as f32 / 2.0;
+        let rh = (h1_rh - h0_rh) / (denom as f32) * (h_out as i32 - self.h0_t0_out as i32) as f32
+            + h0_rh;
+        Some(rh.clamp(0.0, 100.0))
+    }
+
+    /// Converts a raw `TemperatureOut` value into degrees Celsius, using this chip's factory
+    /// calibration.  Returns `None` if the two calibration points share the same ADC reading,
+    /// which would require dividing by zero and indicates an uninitialized or faulty part.
+    pub fn temperature_celsius(&self, t_out: i16) -> Option<f32> {
+        let denom = self.t1_out as i32 - self.t0_out as i32;
+        if denom == 0 {
+            return None;
+        }
+        let t0 = self.t0_deg_c_x8 as f32 / 8.0;
+        let t1 = self.t1_deg_c_x8 as f32 / 8.0;
+        Some((t1 - t0) / (denom as f32) * (t_out as i32 - self.t0_out as i32) as f32 + t0)
+    }
+}
+
+/// Number of microseconds between BOOT-bit polls in [`boot_and_configure`].
+const BOOT_POLL_RETRY_DELAY_US: u32 = 100;
+
+/// Number of BOOT-bit polls to attempt in [`boot_and_configure`] before giving up.  The datasheet
+/// specifies a boot time of up to 2.5 ms; 50 polls of 100 us comfortably covers the worst case.
+const BOOT_POLL_MAX_RETRIES: u32 = 50;
+
+/// Configuration applied by [`boot_and_configure`] once the boot sequence has completed.
+pub struct Config {
+    /// Output data rate (also selects one-shot vs. continuous conversion).
+    pub data_rate: cr1::DataRate,
+    /// Number of internal humidity samples averaged together.
+    pub humidity_avg: av_conf::AvgH,
+    /// Number of internal temperature samples averaged together.
+    pub temperature_avg: av_conf::AvgT,
+    /// Enables block-update mode, so a read of the lower output byte holds off updates until the
+    /// upper byte is also read.
+    pub block_update: bool,
+    /// Drives the DRDY pin low, rather than high, when data is ready.
+    pub data_ready_active_low: bool,
+    /// Configures the DRDY pin as open-drain, rather than push-pull.
+    pub data_ready_open_drain: bool,
+}
+
+/// Sets the BOOT bit and blocks until the hardware clears it, confirming the trimming registers
+/// have been refreshed from Flash, then applies `config` in order (averaging, DRDY pin, then data
+/// rate/block-update/power-up) and returns with the device powered up and ready to measure.
+/// Returns [`PollError::Timeout`] if the BOOT bit does not clear within the retry budget.
+pub fn boot_and_configure<Comm, Delay>(
+    comm: &mut Comm,
+    delay: &mut Delay,
+    config: Config,
+) -> Result<(), PollError<Comm::Error>>
+where
+    Comm: I2C,
+    Delay: DelayUs<u32>,
+{
+    let mut cr2 = CtrlReg2::new(comm)?;
+    cr2.modify(comm, |w| w.boot())?;
+
+    poll_until(
+        delay,
+        BOOT_POLL_RETRY_DELAY_US,
+        BOOT_POLL_MAX_RETRIES,
+        || Ok(!CtrlReg2::new(comm)?.is_booting()),
+    )?;
+
+    let Config {
+        data_rate,
+        humidity_avg,
+        temperature_avg,
+        block_update,
+        data_ready_active_low,
+        data_ready_open_drain,
+    } = config;
+
+    let mut av_conf = AvConf::new(comm)?;
+    av_conf.modify(comm, |w| {
+        w.set_humidity_samples_averaged(humidity_avg);
+        w.set_temperature_samples_averaged(temperature_avg);
+    })?;
+
+    let mut cr3 = CtrlReg3::new(comm)?;
+    cr3.modify(comm, |w| {
+        if data_ready_active_low {
+            w.data_ready_low();
+        } else {
+            w.data_ready_high();
+        }
+        if data_ready_open_drain {
+            w.data_ready_open_drain();
+        } else {
+            w.data_ready_push_pull();
+        }
+    })?;
+
+    let mut cr1 = CtrlReg1::new(comm)?;
+    cr1.modify(comm, |w| {
+        w.set_data_rate(data_rate);
+        if block_update {
+            w.set_block_update();
+        } else {
+            w.set_continuous_update();
+        }
+        w.power_up();
+    })?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calibration() -> Calibration {
+        Calibration {
+            h0_rh_x2: 40,     // 20 %RH
+            h1_rh_x2: 160,    // 80 %RH
+            t0_deg_c_x8: 160, // 20 °C
+            t1_deg_c_x8: 320, // 40 °C
+            h0_t0_out: 0,
+            h1_t0_out: 1000,
+            t0_out: 0,
+            t1_out: 2000,
+        }
+    }
+
+    #[test]
+    fn relative_humidity_interpolates_between_calibration_points() {
+        let cal = calibration();
+        assert_eq!(cal.relative_humidity(0), Some(20.0));
+        assert_eq!(cal.relative_humidity(1000), Some(80.0));
+        assert_eq!(cal.relative_humidity(500), Some(50.0));
+    }
+
+    #[test]
+    fn relative_humidity_clamps_to_0_100() {
+        let cal = calibration();
+        assert_eq!(cal.relative_humidity(-1000), Some(0.0));
+        assert_eq!(cal.relative_humidity(10_000), Some(100.0));
+    }
+
+    #[test]
+    fn relative_humidity_guards_against_zero_denominator() {
+        let mut cal = calibration();
+        cal.h1_t0_out = cal.h0_t0_out;
+        assert_eq!(cal.relative_humidity(0), None);
+    }
+
+    #[test]
+    fn relative_humidity_does_not_overflow_on_extreme_calibration_points() {
+        let mut cal = calibration();
+        cal.h0_t0_out = i16::MIN;
+        cal.h1_t0_out = i16::MAX;
+        assert!(cal.relative_humidity(0).is_some());
+    }
+
+    #[test]
+    fn temperature_celsius_interpolates_between_calibration_points() {
+        let cal = calibration();
+        assert_eq!(cal.temperature_celsius(0), Some(20.0));
+        assert_eq!(cal.temperature_celsius(2000), Some(40.0));
+        assert_eq!(cal.temperature_celsius(1000), Some(30.0));
+    }
+
+    #[test]
+    fn temperature_celsius_guards_against_zero_denominator() {
+        let mut cal = calibration();
+        cal.t1_out = cal.t0_out;
+        assert_eq!(cal.temperature_celsius(0), None);
+    }
+
+    #[test]
+    fn temperature_celsius_does_not_overflow_on_extreme_calibration_points() {
+        let mut cal = calibration();
+        cal.t0_out = i16::MIN;
+        cal.t1_out = i16::MAX;
+        assert!(cal.temperature_celsius(0).is_some());
+    }
 }
@@ -0,0 +1,178 @@
+//! High-level, typestate-checked wrapper around the raw register API in [`crate::device`].
+//!
+//! [`Hts221`] is parameterized by a zero-sized measurement mode marker ([`OneShot`] or
+//! [`Continuous`]), so `measure()` always takes the read path for the mode the device was last
+//! configured into: triggering and polling for a one-shot conversion, or reading the latest
+//! free-running sample.
+
+use core::marker::PhantomData;
+
+use embedded_hal::blocking::delay::DelayUs;
+
+use crate::device::{
+    cr1::DataRate, Calibration, CtrlReg1, CtrlReg2, HumidityOut, PollError, TemperatureOut, I2C,
+};
+
+/// Marker type selecting on-demand, single-shot conversions.
+pub struct OneShot;
+
+/// Marker type selecting free-running conversions at a fixed output data rate.
+pub struct Continuous;
+
+/// Output data rates valid for [`Hts221::into_continuous`].  Unlike [`DataRate`], this has no
+/// one-shot variant, so it's impossible to ask for a `Continuous` wrapper that the hardware would
+/// never actually refresh.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContinuousRate {
+    Hz1,
+    Hz7,
+    Hz12_5,
+}
+
+impl ContinuousRate {
+    fn to_data_rate(self) -> DataRate {
+        match self {
+            ContinuousRate::Hz1 => DataRate::Continuous1Hz,
+            ContinuousRate::Hz7 => DataRate::Continuous7Hz,
+            ContinuousRate::Hz12_5 => DataRate::Continuous12_5Hz,
+        }
+    }
+}
+
+/// A humidity/temperature sample, already converted to physical units using the chip's factory
+/// calibration.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measurement {
+    /// Relative humidity, in percent.
+    pub humidity: f32,
+    /// Temperature, in degrees Celsius.
+    pub temperature: f32,
+}
+
+/// Errors that can occur while using [`Hts221`].
+#[derive(Debug)]
+pub enum Error<E> {
+    /// The underlying I2C transaction failed.
+    I2C(E),
+    /// The device's calibration registers contain identical ADC reference points, so raw samples
+    /// cannot be converted to physical units.
+    InvalidCalibration,
+    /// A one-shot conversion did not complete within its retry budget.
+    Timeout,
+}
+
+impl<E> From<E> for Error<E> {
+    fn from(e: E) -> Self {
+        Error::I2C(e)
+    }
+}
+
+impl<E> From<PollError<E>> for Error<E> {
+    fn from(e: PollError<E>) -> Self {
+        match e {
+            PollError::I2C(e) => Error::I2C(e),
+            PollError::Timeout => Error::Timeout,
+        }
+    }
+}
+
+/// Driver for the HTS221, generic over the communication interface `Comm` and the measurement
+/// mode `Mode`.
+pub struct Hts221<Comm, Mode> {
+    comm: Comm,
+    calibration: Calibration,
+    _mode: PhantomData<Mode>,
+}
+
+impl<Comm: I2C> Hts221<Comm, OneShot> {
+    /// Reads and caches the factory calibration, configures the device for one-shot conversions,
+    /// and powers it up.
+    pub fn new(mut comm: Comm) -> Result<Self, Error<Comm::Error>> {
+        let calibration = Calibration::new(&mut comm)?;
+        let mut cr1 = CtrlReg1::new(&mut comm)?;
+        cr1.modify(&mut comm, |w| {
+            w.set_data_rate(DataRate::OneShot);
+            w.power_up();
+        })?;
+        Ok(Hts221 {
+            comm,
+            calibration,
+            _mode: PhantomData,
+        })
+    }
+
+    /// Triggers a one-shot conversion, then polls STATUS via `delay` (using a retry budget sized
+    /// for [`DataRate::OneShot`]) until both humidity and temperature data are available, reads
+    /// them, and converts the result.
+    pub fn measure<Delay: DelayUs<u32>>(
+        &mut self,
+        delay: &mut Delay,
+    ) -> Result<Measurement, Error<Comm::Error>> {
+        let mut cr2 = CtrlReg2::new(&mut self.comm)?;
+        cr2.modify(&mut self.comm, |w| w.set_one_shot())?;
+
+        let (retry_delay_us, max_retries) = DataRate::OneShot.conversion_timeout();
+        let h_out =
+            HumidityOut::read_when_ready(&mut self.comm, delay, retry_delay_us, max_retries)?
+                .value();
+        let t_out =
+            TemperatureOut::read_when_ready(&mut self.comm, delay, retry_delay_us, max_retries)?
+                .value();
+
+        self.convert(h_out, t_out)
+    }
+
+    /// Reconfigures the device for free-running conversions at `rate` and returns the re-typed
+    /// wrapper.
+    pub fn into_continuous(
+        mut self,
+        rate: ContinuousRate,
+    ) -> Result<Hts221<Comm, Continuous>, Error<Comm::Error>> {
+        let mut cr1 = CtrlReg1::new(&mut self.comm)?;
+        cr1.modify(&mut self.comm, |w| w.set_data_rate(rate.to_data_rate()))?;
+        Ok(Hts221 {
+            comm: self.comm,
+            calibration: self.calibration,
+            _mode: PhantomData,
+        })
+    }
+}
+
+impl<Comm: I2C> Hts221<Comm, Continuous> {
+    /// Reads the latest humidity/temperature sample and converts it using the cached
+    /// calibration.  The device refreshes its output registers on its own at the configured data
+    /// rate; enable block-update mode via [`CtrlReg1`] first if torn reads are a concern.
+    pub fn measure(&mut self) -> Result<Measurement, Error<Comm::Error>> {
+        let h_out = HumidityOut::new(&mut self.comm)?.value();
+        let t_out = TemperatureOut::new(&mut self.comm)?.value();
+        self.convert(h_out, t_out)
+    }
+
+    /// Switches the device back to one-shot mode and returns the re-typed wrapper.
+    pub fn into_one_shot(mut self) -> Result<Hts221<Comm, OneShot>, Error<Comm::Error>> {
+        let mut cr1 = CtrlReg1::new(&mut self.comm)?;
+        cr1.modify(&mut self.comm, |w| w.set_data_rate(DataRate::OneShot))?;
+        Ok(Hts221 {
+            comm: self.comm,
+            calibration: self.calibration,
+            _mode: PhantomData,
+        })
+    }
+}
+
+impl<Comm: I2C, Mode> Hts221<Comm, Mode> {
+    fn convert(&self, h_out: i16, t_out: i16) -> Result<Measurement, Error<Comm::Error>> {
+        let humidity = self
+            .calibration
+            .relative_humidity(h_out)
+            .ok_or(Error::InvalidCalibration)?;
+        let temperature = self
+            .calibration
+            .temperature_celsius(t_out)
+            .ok_or(Error::InvalidCalibration)?;
+        Ok(Measurement {
+            humidity,
+            temperature,
+        })
+    }
+}
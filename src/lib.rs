@@ -0,0 +1,13 @@
+//! A platform-agnostic driver for the ST HTS221 capacitive digital humidity and temperature
+//! sensor.
+//!
+//! The [`device`] module exposes every register defined in the datasheet directly.  The
+//! [`Hts221`] wrapper builds on top of it, caching the factory calibration and tracking the
+//! measurement mode (one-shot or continuous) in the type system.
+
+#![no_std]
+
+pub mod device;
+pub mod hts221;
+
+pub use hts221::{Continuous, ContinuousRate, Error, Hts221, Measurement, OneShot};